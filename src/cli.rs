@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use crate::Result;
+
+/// What to do once the Lua source has been transpiled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Transpile and then hand off to `dnscontrol` with the leftover args.
+    Run,
+    /// Transpile and stop; don't spawn `dnscontrol` at all.
+    TranspileOnly,
+}
+
+/// Parsed command line, with our own flags pulled out and everything else
+/// kept around to forward to the `dnscontrol` subprocess untouched.
+#[derive(Debug, Clone)]
+pub struct UserArgs {
+    pub action: Action,
+    pub lua_path: PathBuf,
+    pub js_path: PathBuf,
+    /// Inline chunks from `-e`, in the order they were given.
+    pub inline_chunks: Vec<String>,
+    pub passthrough_args: Vec<String>,
+}
+
+impl UserArgs {
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let cwd = std::env::current_dir()?;
+
+        let mut action = Action::Run;
+        let mut lua_path = cwd.join("dnscontrol.lua");
+        let mut js_path = cwd.join("dnscontrol.js");
+        let mut inline_chunks = Vec::new();
+        let mut passthrough_args = Vec::new();
+
+        // Skip argv[0]; it's our own binary's path, not something
+        // `dnscontrol` wants to see.
+        let mut iter = args.into_iter().skip(1);
+
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--in" => {
+                    let path = iter
+                        .next()
+                        .ok_or_else(|| color_eyre::eyre::eyre!("--in expects a path argument"))?;
+                    lua_path = PathBuf::from(path);
+                }
+                "--out" => {
+                    let path = iter
+                        .next()
+                        .ok_or_else(|| color_eyre::eyre::eyre!("--out expects a path argument"))?;
+                    js_path = PathBuf::from(path);
+                }
+                "-e" => {
+                    let chunk = iter.next().ok_or_else(|| {
+                        color_eyre::eyre::eyre!("-e expects a Lua chunk argument")
+                    })?;
+                    inline_chunks.push(chunk);
+                }
+                "--transpile-only" => {
+                    action = Action::TranspileOnly;
+                }
+                _ => passthrough_args.push(arg),
+            }
+        }
+
+        Ok(Self {
+            action,
+            lua_path,
+            js_path,
+            inline_chunks,
+            passthrough_args,
+        })
+    }
+}
@@ -1,36 +1,65 @@
-use std::{
-    fs::File,
-    io::{BufRead, Cursor, Write},
-    path::{Path, PathBuf},
-    process::Command,
-};
+mod cli;
+mod runtime;
+mod sourcemap;
+
+use std::{fmt::Write as _, fs::File, io::Write, path::Path, process::Command};
 
 use color_eyre::eyre::eyre;
 
+use cli::{Action, UserArgs};
+use runtime::UsedBuiltins;
+use sourcemap::{Mapping, SourceMap};
+
 type Result<T> = color_eyre::eyre::Result<T>;
 
 fn main() -> Result<()> {
     let _ = color_eyre::install();
 
-    let mut out = File::create(js_path()?)?;
-    luaize(&lua_path()?, &mut out)?;
+    let args = UserArgs::parse(std::env::args())?;
 
-    let mut cmd = Command::new("dnscontrol");
-    for arg in std::env::args() {
-        cmd.arg(arg);
+    let source = gather_source(&args)?;
+    let output = luaize_source(&source, &args.lua_path, &args.js_path)?;
+
+    let mut out = File::create(&args.js_path)?;
+    write!(out, "{}", output.js)?;
+
+    let map_path = map_path_for(&args.js_path);
+    std::fs::write(&map_path, output.source_map.to_json())?;
+
+    if args.action == Action::TranspileOnly {
+        return Ok(());
     }
 
+    let mut cmd = Command::new("dnscontrol");
+    cmd.args(&args.passthrough_args);
+
     let mut child = cmd.spawn()?;
     child.wait()?;
     Ok(())
 }
 
-fn lua_path() -> Result<PathBuf> {
-    Ok(std::env::current_dir()?.join("dnscontrol.lua"))
+fn map_path_for(js_path: &Path) -> std::path::PathBuf {
+    let mut name = js_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".map");
+    js_path.with_file_name(name)
 }
 
-fn js_path() -> Result<PathBuf> {
-    Ok(std::env::current_dir()?.join("dnscontrol.js"))
+/// Concatenates the Lua source file (if it exists) with any `-e` chunks, in
+/// the order they were given on the command line.
+fn gather_source(args: &UserArgs) -> Result<String> {
+    let mut source = String::new();
+
+    if args.lua_path.exists() {
+        source += &std::fs::read_to_string(&args.lua_path)?;
+        source.push('\n');
+    }
+
+    for chunk in &args.inline_chunks {
+        source += chunk;
+        source.push('\n');
+    }
+
+    Ok(source)
 }
 
 fn funcall_to_string(expr: &lua_parser::ExprFunctionCall) -> Result<String> {
@@ -63,6 +92,26 @@ fn funcall_to_string(expr: &lua_parser::ExprFunctionCall) -> Result<String> {
     Ok(s)
 }
 
+/// Renders the right-hand side of a parallel assignment or local
+/// declaration. A single value is emitted as-is, so a single call that
+/// returns multiple results (already an array per the runtime's multi-return
+/// convention) destructures directly; two or more values are wrapped into an
+/// array literal for the same destructuring target.
+fn rhs_to_str(values: &[lua_parser::Expression]) -> Result<String> {
+    if values.len() == 1 {
+        expr_to_str(&values[0])
+    } else {
+        Ok(format!(
+            "[{}]",
+            values
+                .iter()
+                .map(expr_to_str)
+                .collect::<Result<Vec<_>>>()?
+                .join(", ")
+        ))
+    }
+}
+
 fn expr_to_str(expr: &lua_parser::Expression) -> Result<String> {
     let mut s = String::new();
 
@@ -93,9 +142,9 @@ fn expr_to_str(expr: &lua_parser::Expression) -> Result<String> {
         }
         Expression::Unary(expr) => match expr {
             ExprUnary::Length(expr) => {
-                s += "(";
+                s += "__len(";
                 s += &expr_to_str(&expr.value)?;
-                s += ".length)";
+                s += ")";
             }
             ExprUnary::Minus(expr) => {
                 s += "(-";
@@ -111,36 +160,64 @@ fn expr_to_str(expr: &lua_parser::Expression) -> Result<String> {
                 return Err(eyre!("Expression currently unsupported: {:?}", other));
             }
         },
-        Expression::Binary(expr) => {
-            let (lhs, rhs, op) = match expr.clone() {
-                ExprBinary::Add(expr) => (expr.lhs, expr.rhs, "+"),
-                ExprBinary::Sub(expr) => (expr.lhs, expr.rhs, "-"),
-                ExprBinary::Mul(expr) => (expr.lhs, expr.rhs, "*"),
-                ExprBinary::Div(expr) => (expr.lhs, expr.rhs, "/"),
-                ExprBinary::FloorDiv(expr) => (expr.lhs, expr.rhs, "/"), // TODO: differentiate from regular division...
-                ExprBinary::Mod(expr) => (expr.lhs, expr.rhs, "*"),
-                ExprBinary::Concat(expr) => (expr.lhs, expr.rhs, "+"),
-                ExprBinary::Equal(expr) => (expr.lhs, expr.rhs, "==="),
-                ExprBinary::NotEqual(expr) => (expr.lhs, expr.rhs, "!=="),
-                ExprBinary::GreaterThan(expr) => (expr.lhs, expr.rhs, ">"),
-                ExprBinary::GreaterEqual(expr) => (expr.lhs, expr.rhs, ">="),
-                ExprBinary::LessThan(expr) => (expr.lhs, expr.rhs, "<"),
-                ExprBinary::LessEqual(expr) => (expr.lhs, expr.rhs, "<="),
-                other => return Err(eyre!("Unsupported binary operator: {:?}", other)),
-            };
+        Expression::Binary(expr) => match expr.clone() {
+            // Lua's `%` takes the sign of the divisor, unlike JS's `%` which
+            // takes the sign of the dividend.
+            ExprBinary::Mod(expr) => {
+                let lhs = expr_to_str(&expr.lhs)?;
+                let rhs = expr_to_str(&expr.rhs)?;
+                s += &format!("((({lhs} % {rhs}) + {rhs}) % {rhs})");
+            }
+            // `//` floors towards negative infinity, which plain JS `/`
+            // doesn't do for negative operands.
+            ExprBinary::FloorDiv(expr) => {
+                let lhs = expr_to_str(&expr.lhs)?;
+                let rhs = expr_to_str(&expr.rhs)?;
+                s += &format!("Math.floor({lhs} / {rhs})");
+            }
+            // `..` stringifies both operands; JS `+` only does that when one
+            // side is already a string.
+            ExprBinary::Concat(expr) => {
+                let lhs = expr_to_str(&expr.lhs)?;
+                let rhs = expr_to_str(&expr.rhs)?;
+                s += &format!("__concat({lhs}, {rhs})");
+            }
+            other => {
+                let (lhs, rhs, op) = match other {
+                    ExprBinary::Add(expr) => (expr.lhs, expr.rhs, "+"),
+                    ExprBinary::Sub(expr) => (expr.lhs, expr.rhs, "-"),
+                    ExprBinary::Mul(expr) => (expr.lhs, expr.rhs, "*"),
+                    ExprBinary::Div(expr) => (expr.lhs, expr.rhs, "/"),
+                    // Tables compare by identity and primitives compare by
+                    // value under both `===`/`!==` and Lua's `==`/`~=`, so
+                    // JS's own operators already do the right thing here.
+                    ExprBinary::Equal(expr) => (expr.lhs, expr.rhs, "==="),
+                    ExprBinary::NotEqual(expr) => (expr.lhs, expr.rhs, "!=="),
+                    ExprBinary::GreaterThan(expr) => (expr.lhs, expr.rhs, ">"),
+                    ExprBinary::GreaterEqual(expr) => (expr.lhs, expr.rhs, ">="),
+                    ExprBinary::LessThan(expr) => (expr.lhs, expr.rhs, "<"),
+                    ExprBinary::LessEqual(expr) => (expr.lhs, expr.rhs, "<="),
+                    other => return Err(eyre!("Unsupported binary operator: {:?}", other)),
+                };
 
-            s += "(";
-            s += &expr_to_str(&lhs)?;
-            s += op;
-            s += &expr_to_str(&rhs)?;
-            s += ")";
-        }
+                s += "(";
+                s += &expr_to_str(&lhs)?;
+                s += op;
+                s += &expr_to_str(&rhs)?;
+                s += ")";
+            }
+        },
         Expression::FunctionCall(expr) => {
             s = funcall_to_string(expr)?;
         }
         Expression::Table(expr) => {
+            // Every table becomes an object, with positional (bare-value)
+            // entries keyed by their 1-based Lua index. This keeps `t[1]`
+            // consistent between list-style and mixed tables; `ipairs`/`#t`
+            // (via `__len`) are written against this same representation.
             s += "({";
 
+            let mut next_index = 1u64;
             let mut iter = expr.fields.iter().peekable();
             while let Some(field) = iter.next() {
                 match field {
@@ -155,8 +232,11 @@ fn expr_to_str(expr: &lua_parser::Expression) -> Result<String> {
                         s += "\": ";
                         s += &expr_to_str(&nv.value)?;
                     }
-                    TableField::Value(_) => {
-                        return Err(eyre!("Table value without a key currently unsupported"));
+                    TableField::Value(field) => {
+                        s += &next_index.to_string();
+                        s += ": ";
+                        s += &expr_to_str(&field.value)?;
+                        next_index += 1;
                     }
                 };
 
@@ -180,6 +260,32 @@ fn expr_to_str(expr: &lua_parser::Expression) -> Result<String> {
     Ok(s)
 }
 
+/// The originating Lua position of a statement, used to populate the source
+/// map. `lua_parser` spans are 1-based; we convert to the 0-based positions
+/// Source Map v3 expects right before emitting a `Mapping`.
+fn stmt_span(stmt: &lua_parser::Statement) -> lua_parser::Span {
+    use lua_parser::Statement::*;
+
+    match stmt {
+        None(stmt) => stmt.span,
+        Assignment(stmt) => stmt.span,
+        LocalDeclaration(stmt) => stmt.span,
+        If(stmt) => stmt.span,
+        While(stmt) => stmt.span,
+        For(stmt) => stmt.span,
+        GenericFor(stmt) => stmt.span,
+        Break(stmt) => stmt.span,
+        Do(stmt) => stmt.span,
+        FunctionCall(stmt) => stmt.span,
+        FunctionDefinition(stmt) => stmt.span,
+        FunctionDefinitionLocal(stmt) => stmt.span,
+        _ => Default::default(),
+    }
+}
+
+/// Renders a `lua_parser::Block` to JS, one statement at a time, tracking
+/// which generated line each statement's JS came from so `luaize_source` can
+/// build a source map alongside the output.
 struct BlockWriter {
     indent: usize,
 }
@@ -189,98 +295,163 @@ impl BlockWriter {
         Self { indent: 0 }
     }
 
-    fn write_block(&mut self, out: &mut dyn Write, block: &lua_parser::Block) -> Result<()> {
+    /// Renders `block`'s own statements, with every line indented by the
+    /// writer's depth on entry (`self.indent` four-space levels), and returns
+    /// the JS text plus mappings relative to line 0 of that text (their
+    /// columns already include that indent). If this is a nested block, the
+    /// caller is responsible for shifting the mappings by however many lines
+    /// precede it.
+    fn write_block(&mut self, block: &lua_parser::Block) -> Result<(String, Vec<Mapping>)> {
+        use lua_parser::Expression;
         use lua_parser::Statement::*;
 
-        let real_out = out;
-
-        let mut buf = Vec::new();
-        let out = &mut Cursor::new(&mut buf);
+        let mut text = String::new();
+        let mut mappings = Vec::new();
 
         self.indent += 1;
 
         for stmt in &block.statements {
+            self.mark(&mut text, &mut mappings, stmt_span(stmt));
+
             match stmt {
                 None(_) => {
-                    writeln!(out, ";")?;
+                    writeln!(text, ";")?;
                 }
                 Assignment(stmt) => {
-                    if stmt.lhs.len() > 1 || stmt.rhs.len() > 1 {
-                        return Err(eyre!("Parallel assignment currently unsupported"));
+                    if stmt.lhs.len() == 1 && stmt.rhs.len() == 1 {
+                        writeln!(
+                            text,
+                            "{} = {};",
+                            expr_to_str(&stmt.lhs[0])?,
+                            expr_to_str(&stmt.rhs[0])?
+                        )?;
+                    } else {
+                        let targets = stmt
+                            .lhs
+                            .iter()
+                            .map(expr_to_str)
+                            .collect::<Result<Vec<_>>>()?
+                            .join(", ");
+                        writeln!(text, "[{}] = {};", targets, rhs_to_str(&stmt.rhs)?)?;
                     }
-
-                    writeln!(
-                        out,
-                        "{} = {};",
-                        expr_to_str(&stmt.lhs[0])?,
-                        expr_to_str(&stmt.rhs[0])?
-                    )?;
                 }
                 LocalDeclaration(stmt) => {
-                    if stmt.names.len() > 1 {
-                        return Err(eyre!("Local multiple declarations currently unsupported"));
-                    }
                     let Some(values) = &stmt.values else {
                         return Err(eyre!("Local declarations without assignment unsupported"));
                     };
-                    writeln!(
-                        out,
-                        "var {} = {};",
-                        stmt.names[0].name,
-                        expr_to_str(&values[0])?
-                    )?;
+
+                    if stmt.names.len() == 1 && values.len() == 1 {
+                        writeln!(
+                            text,
+                            "var {} = {};",
+                            stmt.names[0].name,
+                            expr_to_str(&values[0])?
+                        )?;
+                    } else {
+                        let names = stmt
+                            .names
+                            .iter()
+                            .map(|name| name.name.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        writeln!(text, "var [{}] = {};", names, rhs_to_str(values)?)?;
+                    }
                 }
                 If(stmt) => {
-                    writeln!(out, "if ({}) {{", expr_to_str(&stmt.condition)?)?;
-                    self.write_block(out, &stmt.block)?;
-                    writeln!(out, "}}")?;
+                    writeln!(text, "if ({}) {{", expr_to_str(&stmt.condition)?)?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                    writeln!(text, "}}")?;
 
                     for stmt in &stmt.else_ifs {
-                        writeln!(out, "else if ({}) {{", expr_to_str(&stmt.condition)?)?;
-                        self.write_block(out, &stmt.block)?;
-                        writeln!(out, "}}")?;
+                        writeln!(text, "else if ({}) {{", expr_to_str(&stmt.condition)?)?;
+                        self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                        writeln!(text, "}}")?;
                     }
 
                     if let Some(block) = &stmt.else_block {
-                        writeln!(out, "else {{")?;
-                        self.write_block(out, &block)?;
-                        writeln!(out, "}}")?;
+                        writeln!(text, "else {{")?;
+                        self.embed_block(&mut text, &mut mappings, block)?;
+                        writeln!(text, "}}")?;
                     }
                 }
                 While(stmt) => {
-                    writeln!(out, "while ({}) {{", expr_to_str(&stmt.condition)?)?;
-                    self.write_block(out, &stmt.block)?;
-                    writeln!(out, "}}")?;
+                    writeln!(text, "while ({}) {{", expr_to_str(&stmt.condition)?)?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                    writeln!(text, "}}")?;
                 }
                 For(stmt) => {
+                    // Lua evaluates `start`/`end`/`step` exactly once and
+                    // picks the loop direction from the sign of `step`, so
+                    // stash them in temporaries rather than re-evaluating
+                    // (or mis-comparing) them on every iteration.
+                    let end_var = format!("__{}_end", stmt.name);
+                    let step_var = format!("__{}_step", stmt.name);
+
+                    writeln!(text, "{{")?;
+                    writeln!(text, "var {} = {};", end_var, expr_to_str(&stmt.end)?)?;
+                    writeln!(text, "var {} = {};", step_var, expr_to_str(&stmt.step)?)?;
                     writeln!(
-                        out,
-                        "for (var {} = {}; {}; {}) {{",
+                        text,
+                        "for (var {0} = {1}; {2} >= 0 ? {0} <= {3} : {0} >= {3}; {0} += {2}) {{",
                         stmt.name,
                         expr_to_str(&stmt.start)?,
-                        expr_to_str(&stmt.end)?,
-                        expr_to_str(&stmt.step)?
+                        step_var,
+                        end_var
                     )?;
-                    self.write_block(out, &stmt.block)?;
-                    writeln!(out, "}}")?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                    writeln!(text, "}}")?;
+                    writeln!(text, "}}")?;
+                }
+                GenericFor(stmt) => {
+                    let Expression::FunctionCall(call) = &stmt.exprs[0] else {
+                        return Err(eyre!(
+                            "Generic for currently only supports 'pairs'/'ipairs' iterators"
+                        ));
+                    };
+                    let Expression::Ident(iterator) = call.prefix.as_ref() else {
+                        return Err(eyre!(
+                            "Generic for currently only supports 'pairs'/'ipairs' iterators"
+                        ));
+                    };
+                    if iterator.name.as_str() != "pairs" && iterator.name.as_str() != "ipairs" {
+                        return Err(eyre!(
+                            "Generic for currently only supports 'pairs'/'ipairs' iterators"
+                        ));
+                    }
+
+                    let names = stmt
+                        .names
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    writeln!(
+                        text,
+                        "for (const [{}] of {}) {{",
+                        names,
+                        funcall_to_string(call)?
+                    )?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                    writeln!(text, "}}")?;
                 }
                 Break(_) => {
-                    writeln!(out, "break;")?;
+                    writeln!(text, "break;")?;
                 }
                 Do(stmt) => {
-                    writeln!(out, "(function() {{")?;
-                    self.write_block(out, &stmt.block)?;
-                    writeln!(out, "}})();")?;
+                    writeln!(text, "(function() {{")?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.block)?;
+                    writeln!(text, "}})();")?;
                 }
                 FunctionCall(expr) => {
-                    writeln!(out, "{};", &funcall_to_string(expr)?)?;
+                    writeln!(text, "{};", &funcall_to_string(expr)?)?;
                 }
                 FunctionDefinition(stmt) => {
                     if stmt.body.parameters.variadic {
                         return Err(eyre!("Variadic functions currently unsupported"));
                     }
                     writeln!(
-                        out,
+                        text,
                         "function {}({}) {{",
                         stmt.name.names[0],
                         stmt.body
@@ -291,15 +462,15 @@ impl BlockWriter {
                             .collect::<Vec<_>>()
                             .join(", ")
                     )?;
-                    self.write_block(out, &stmt.body.block)?;
-                    writeln!(out, "}}")?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.body.block)?;
+                    writeln!(text, "}}")?;
                 }
                 FunctionDefinitionLocal(stmt) => {
                     if stmt.body.parameters.variadic {
                         return Err(eyre!("Variadic functions currently unsupported"));
                     }
                     writeln!(
-                        out,
+                        text,
                         "var {} = ({}) => {{",
                         stmt.name,
                         stmt.body
@@ -310,8 +481,8 @@ impl BlockWriter {
                             .collect::<Vec<_>>()
                             .join(", ")
                     )?;
-                    self.write_block(out, &stmt.body.block)?;
-                    writeln!(out, "}};")?;
+                    self.embed_block(&mut text, &mut mappings, &stmt.body.block)?;
+                    writeln!(text, "}};")?;
                 }
                 other => {
                     return Err(eyre!("Statement unsupported: {:?}", other));
@@ -320,28 +491,121 @@ impl BlockWriter {
         }
 
         if let Some(stmt) = &block.return_statement {
-            if stmt.values.len() > 1 {
-                return Err(eyre!("Multiple return values currently unsupported"));
+            self.mark(&mut text, &mut mappings, stmt.span);
+            if stmt.values.is_empty() {
+                writeln!(text, "return;")?;
+            } else if stmt.values.len() == 1 {
+                writeln!(text, "return {};", expr_to_str(&stmt.values[0])?)?;
+            } else {
+                writeln!(text, "return {};", rhs_to_str(&stmt.values)?)?;
             }
-            writeln!(out, "return {};", expr_to_str(&stmt.values[0])?)?;
         }
 
         self.indent -= 1;
 
-        for line in buf.lines() {
-            let line = line?;
-            let indent = "    ".repeat(self.indent);
-            writeln!(real_out, "{}{}", indent, line)?;
+        let indent = "    ".repeat(self.indent);
+        let indented = text
+            .lines()
+            .map(|line| format!("{}{}\n", indent, line))
+            .collect::<String>();
+
+        // Every line just gained `indent.len()` columns, including lines
+        // that came from an already-returned nested block (whose own
+        // mappings already account for its own, smaller indent) -- so bump
+        // every mapping we're about to return, not just the ones `mark`
+        // recorded directly in this call.
+        for mapping in &mut mappings {
+            mapping.generated_column += indent.len() as u32;
         }
 
+        Ok((indented, mappings))
+    }
+
+    /// Renders a nested block and splices it into `text` at its current
+    /// position, shifting the nested mappings so they land on the right
+    /// generated line of the *caller's* output.
+    fn embed_block(
+        &mut self,
+        text: &mut String,
+        mappings: &mut Vec<Mapping>,
+        block: &lua_parser::Block,
+    ) -> Result<()> {
+        let line_offset = text.lines().count() as u32;
+        let (nested_text, nested_mappings) = self.write_block(block)?;
+
+        text.push_str(&nested_text);
+        mappings.extend(nested_mappings.into_iter().map(|mut m| {
+            m.generated_line += line_offset;
+            m
+        }));
+
         Ok(())
     }
+
+    /// Records where the next bytes written to `text` came from in the Lua
+    /// source.
+    fn mark(&self, text: &mut String, mappings: &mut Vec<Mapping>, span: lua_parser::Span) {
+        mappings.push(Mapping {
+            generated_line: text.lines().count() as u32,
+            generated_column: 0,
+            source_line: span.line.saturating_sub(1),
+            source_column: span.column.saturating_sub(1),
+        });
+    }
+}
+
+struct LuaizeOutput {
+    js: String,
+    source_map: SourceMap,
 }
 
 pub fn luaize(path: &Path, out: &mut dyn Write) -> Result<()> {
-    let source = std::fs::read(path)?;
-    let ast = lua_parser::parse_bytes(&source)?;
-    return BlockWriter::new().write_block(out, &ast);
+    let source = std::fs::read_to_string(path)?;
+    let output = luaize_source(&source, path, &path.with_extension("js"))?;
+    write!(out, "{}", output.js)?;
+    Ok(())
+}
+
+fn luaize_source(source: &str, lua_path: &Path, js_path: &Path) -> Result<LuaizeOutput> {
+    let ast = lua_parser::parse_bytes(source.as_bytes())?;
+
+    let prelude = UsedBuiltins::scan(&ast).prelude();
+
+    let (body, mut mappings) = BlockWriter::new().write_block(&ast)?;
+    let prelude_lines = if prelude.is_empty() {
+        0
+    } else {
+        prelude.lines().count() as u32 + 1
+    };
+    for mapping in &mut mappings {
+        mapping.generated_line += prelude_lines;
+    }
+
+    let js_file_name = js_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "dnscontrol.js".to_string());
+    let source_name = lua_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "dnscontrol.lua".to_string());
+
+    let mut js = String::new();
+    if !prelude.is_empty() {
+        js += &prelude;
+        js += "\n\n";
+    }
+    js += &body;
+    js += &format!("//# sourceMappingURL={}.map\n", js_file_name);
+
+    let source_map = SourceMap {
+        file: js_file_name,
+        source: source_name,
+        source_content: source.to_string(),
+        mappings,
+    };
+
+    Ok(LuaizeOutput { js, source_map })
 }
 
 #[cfg(test)]
@@ -368,6 +632,17 @@ mod tests {
         super::luaize(&inpath, &mut out).expect("Failed to luaize");
     }
 
+    fn transpile(name: &str) -> String {
+        let _ = color_eyre::install();
+
+        let inpath = test_path(name);
+        println!("Testing {:?}", inpath);
+
+        let mut out = Vec::new();
+        super::luaize(&inpath, &mut out).expect("Failed to luaize");
+        String::from_utf8(out).expect("transpiled JS is not valid UTF-8")
+    }
+
     macro_rules! test {
         ($name:ident) => {
             #[test]
@@ -379,4 +654,44 @@ mod tests {
 
     test!(basic);
     test!(colon);
+
+    #[test]
+    fn negative_modulo() {
+        // Lua's `%` takes the sign of the divisor: `-7 % 3` is `2`, not `-1`.
+        let js = transpile("negative_modulo");
+        assert!(
+            js.contains("((((-7) % 3) + 3) % 3)"),
+            "expected sign-of-divisor modulo, got:\n{}",
+            js
+        );
+    }
+
+    #[test]
+    fn mixed_concat() {
+        // `..` stringifies both operands via the runtime shim rather than
+        // using JS `+`, so numbers concatenate correctly.
+        let js = transpile("mixed_concat");
+        assert!(
+            js.contains("__concat("),
+            "expected a call to __concat:\n{}",
+            js
+        );
+        assert!(
+            js.contains("function tostring("),
+            "expected the tostring shim to be pulled in transitively:\n{}",
+            js
+        );
+    }
+
+    #[test]
+    fn list_index() {
+        // List literals use 1-based integer keys, the same as mixed tables,
+        // so `t[1]` reaches the first element rather than the second.
+        let js = transpile("list_index");
+        assert!(
+            js.contains("({1: \"\\x61\", 2: \"\\x62\", 3: \"\\x63\"})"),
+            "expected a 1-based-keyed object literal, got:\n{}",
+            js
+        );
+    }
 }
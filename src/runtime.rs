@@ -0,0 +1,305 @@
+use std::collections::BTreeSet;
+
+use lua_parser::{
+    Block, ExprBinary, ExprFunctionCall, ExprUnary, Expression, Statement, TableField,
+};
+
+/// JS source for each standalone global shim, keyed by the Lua identifier
+/// that triggers it.
+const GLOBAL_SHIMS: &[(&str, &str)] = &[
+    (
+        "tostring",
+        "function tostring(v) {\n    if (v === undefined || v === null) return \"nil\";\n    if (typeof v === \"boolean\") return v ? \"true\" : \"false\";\n    return String(v);\n}",
+    ),
+    (
+        "ipairs",
+        "function ipairs(t) {\n    var out = [];\n    var i = 1;\n    while (Object.prototype.hasOwnProperty.call(t, i)) {\n        out.push([i, t[i]]);\n        i++;\n    }\n    return out;\n}",
+    ),
+    (
+        "pairs",
+        "function pairs(t) {\n    return Object.keys(t).map(function(k) { return [k, t[k]]; });\n}",
+    ),
+    (
+        "__concat",
+        "function __concat(a, b) {\n    return tostring(a) + tostring(b);\n}",
+    ),
+    (
+        "__len",
+        "function __len(t) {\n    var n = 0;\n    while (Object.prototype.hasOwnProperty.call(t, n + 1)) n++;\n    return n;\n}",
+    ),
+];
+
+/// JS source for the `string` and `table` namespace objects, one entry per
+/// method so we only emit the ones actually referenced.
+const NAMESPACE_SHIMS: &[(&str, &str, &str)] = &[
+    (
+        "string",
+        "sub",
+        "sub: function(s, i, j) {\n        var len = s.length;\n        if (i < 0) i = Math.max(len + i + 1, 1);\n        if (i < 1) i = 1;\n        if (j === undefined) j = len;\n        if (j < 0) j = len + j + 1;\n        if (j > len) j = len;\n        return s.slice(i - 1, j);\n    }",
+    ),
+    (
+        "string",
+        "format",
+        "format: function(fmt) {\n        var args = Array.prototype.slice.call(arguments, 1);\n        var i = 0;\n        return fmt.replace(/%[sdx%]/g, function(spec) {\n            if (spec === \"%%\") return \"%\";\n            var arg = args[i++];\n            if (spec === \"%d\") return String(Math.trunc(arg));\n            if (spec === \"%x\") return Math.trunc(arg).toString(16);\n            return tostring(arg);\n        });\n    }",
+    ),
+    (
+        "table",
+        "insert",
+        "insert: function(t, a, b) {\n        if (b === undefined) {\n            t[__len(t) + 1] = a;\n        } else {\n            for (var i = __len(t) + 1; i > a; i--) {\n                t[i] = t[i - 1];\n            }\n            t[a] = b;\n        }\n    }",
+    ),
+];
+
+/// `math` is emitted as a whole once anything under it is used, since Lua
+/// programs tend to reach for several of its members at once.
+const MATH_SHIM: &str = "var math = {\n    floor: Math.floor,\n    ceil: Math.ceil,\n    abs: Math.abs,\n    sqrt: Math.sqrt,\n    max: Math.max,\n    min: Math.min,\n    random: Math.random,\n    huge: Infinity,\n    pi: Math.PI,\n};";
+
+/// Other global shims a given global shim's JS body calls into. Resolved
+/// transitively by `mark_global` so e.g. marking `__concat` also pulls in
+/// `tostring`, rather than relying on the Lua source happening to name it.
+const GLOBAL_DEPENDENCIES: &[(&str, &[&str])] = &[("__concat", &["tostring"])];
+
+/// Same idea as `GLOBAL_DEPENDENCIES`, but for namespace members (e.g.
+/// `string.format` also calls `tostring`, and `table.insert` calls `__len`
+/// to find the next index in the object-backed table representation).
+const NAMESPACE_DEPENDENCIES: &[(&str, &str, &[&str])] = &[
+    ("string", "format", &["tostring"]),
+    ("table", "insert", &["__len"]),
+];
+
+/// Which Lua builtins a transpiled chunk actually touches.
+#[derive(Default)]
+pub struct UsedBuiltins {
+    globals: BTreeSet<&'static str>,
+    namespace_members: BTreeSet<(&'static str, &'static str)>,
+    math: bool,
+}
+
+impl UsedBuiltins {
+    pub fn scan(block: &Block) -> Self {
+        let mut used = Self::default();
+        used.scan_block(block);
+        used
+    }
+
+    /// Renders the JS prelude for exactly the builtins seen during the scan.
+    pub fn prelude(&self) -> String {
+        let mut chunks = Vec::new();
+
+        for (name, src) in GLOBAL_SHIMS {
+            if self.globals.contains(name) {
+                chunks.push(src.to_string());
+            }
+        }
+
+        for namespace in ["string", "table"] {
+            let members: Vec<_> = NAMESPACE_SHIMS
+                .iter()
+                .filter(|(ns, member, _)| {
+                    *ns == namespace && self.namespace_members.contains(&(*ns, *member))
+                })
+                .map(|(_, _, src)| src.to_string())
+                .collect();
+
+            if !members.is_empty() {
+                chunks.push(format!(
+                    "var {} = {{\n    {}\n}};",
+                    namespace,
+                    members.join(",\n    ")
+                ));
+            }
+        }
+
+        if self.math {
+            chunks.push(MATH_SHIM.to_string());
+        }
+
+        chunks.join("\n\n")
+    }
+
+    fn mark_global(&mut self, name: &str) {
+        let Some((shim, _)) = GLOBAL_SHIMS.iter().find(|(n, _)| *n == name) else {
+            return;
+        };
+
+        // Only chase dependencies the first time we see this shim, so a
+        // dependency cycle (there isn't one today, but there might be)
+        // can't recurse forever.
+        if !self.globals.insert(shim) {
+            return;
+        }
+
+        if let Some((_, deps)) = GLOBAL_DEPENDENCIES.iter().find(|(n, _)| *n == name) {
+            for dep in *deps {
+                self.mark_global(dep);
+            }
+        }
+    }
+
+    fn mark_namespace_member(&mut self, namespace: &str, member: &str) {
+        if namespace == "math" {
+            self.math = true;
+            return;
+        }
+
+        let Some((ns, m, _)) = NAMESPACE_SHIMS
+            .iter()
+            .find(|(ns, m, _)| *ns == namespace && *m == member)
+        else {
+            return;
+        };
+
+        if !self.namespace_members.insert((ns, m)) {
+            return;
+        }
+
+        if let Some((_, _, deps)) = NAMESPACE_DEPENDENCIES
+            .iter()
+            .find(|(n, mm, _)| *n == namespace && *mm == member)
+        {
+            for dep in *deps {
+                self.mark_global(dep);
+            }
+        }
+    }
+
+    fn scan_block(&mut self, block: &Block) {
+        use Statement::*;
+
+        for stmt in &block.statements {
+            match stmt {
+                None(_) | Break(_) => {}
+                Assignment(stmt) => {
+                    for e in &stmt.lhs {
+                        self.scan_expr(e);
+                    }
+                    for e in &stmt.rhs {
+                        self.scan_expr(e);
+                    }
+                }
+                LocalDeclaration(stmt) => {
+                    if let Some(values) = &stmt.values {
+                        for e in values {
+                            self.scan_expr(e);
+                        }
+                    }
+                }
+                If(stmt) => {
+                    self.scan_expr(&stmt.condition);
+                    self.scan_block(&stmt.block);
+                    for branch in &stmt.else_ifs {
+                        self.scan_expr(&branch.condition);
+                        self.scan_block(&branch.block);
+                    }
+                    if let Some(block) = &stmt.else_block {
+                        self.scan_block(block);
+                    }
+                }
+                While(stmt) => {
+                    self.scan_expr(&stmt.condition);
+                    self.scan_block(&stmt.block);
+                }
+                For(stmt) => {
+                    self.scan_expr(&stmt.start);
+                    self.scan_expr(&stmt.end);
+                    self.scan_expr(&stmt.step);
+                    self.scan_block(&stmt.block);
+                }
+                GenericFor(stmt) => {
+                    for e in &stmt.exprs {
+                        self.scan_expr(e);
+                    }
+                    self.scan_block(&stmt.block);
+                }
+                Do(stmt) => self.scan_block(&stmt.block),
+                FunctionCall(call) => self.scan_funcall(call),
+                FunctionDefinition(stmt) => self.scan_block(&stmt.body.block),
+                FunctionDefinitionLocal(stmt) => self.scan_block(&stmt.body.block),
+                _ => {}
+            }
+        }
+
+        if let Some(stmt) = &block.return_statement {
+            for e in &stmt.values {
+                self.scan_expr(e);
+            }
+        }
+    }
+
+    fn scan_funcall(&mut self, call: &ExprFunctionCall) {
+        self.scan_expr(&call.prefix);
+        for arg in &call.args.args {
+            self.scan_expr(arg);
+        }
+    }
+
+    fn scan_expr(&mut self, expr: &Expression) {
+        use Expression::*;
+
+        match expr {
+            Ident(e) => self.mark_global(&e.name),
+            Bool(_) | Numeric(_) | Nil(_) | String(_) => {}
+            Unary(e) => match e {
+                ExprUnary::Length(e) => {
+                    self.mark_global("__len");
+                    self.scan_expr(&e.value);
+                }
+                ExprUnary::Minus(e) | ExprUnary::Plus(e) => self.scan_expr(&e.value),
+                _ => {}
+            },
+            Binary(e) => {
+                // `..` becomes a call into `__concat`, same as any other
+                // builtin we splice a shim in for.
+                if matches!(e, ExprBinary::Concat(_)) {
+                    self.mark_global("__concat");
+                }
+                self.scan_binary(e);
+            }
+            FunctionCall(call) => self.scan_funcall(call),
+            Table(t) => {
+                for field in &t.fields {
+                    match field {
+                        TableField::KeyValue(kv) => {
+                            self.scan_expr(&kv.key);
+                            self.scan_expr(&kv.value);
+                        }
+                        TableField::NameValue(nv) => self.scan_expr(&nv.value),
+                        TableField::Value(v) => self.scan_expr(&v.value),
+                    }
+                }
+            }
+            TableIndex(e) => {
+                if let (Expression::Ident(table), Expression::String(index)) =
+                    (e.table.as_ref(), e.index.as_ref())
+                {
+                    let member = String::from_utf8_lossy(&index.value).into_owned();
+                    self.mark_namespace_member(&table.name, &member);
+                }
+                self.scan_expr(&e.table);
+                self.scan_expr(&e.index);
+            }
+            _ => {}
+        }
+    }
+
+    fn scan_binary(&mut self, expr: &ExprBinary) {
+        let (lhs, rhs) = match expr {
+            ExprBinary::Add(e)
+            | ExprBinary::Sub(e)
+            | ExprBinary::Mul(e)
+            | ExprBinary::Div(e)
+            | ExprBinary::FloorDiv(e)
+            | ExprBinary::Mod(e)
+            | ExprBinary::Concat(e)
+            | ExprBinary::Equal(e)
+            | ExprBinary::NotEqual(e)
+            | ExprBinary::GreaterThan(e)
+            | ExprBinary::GreaterEqual(e)
+            | ExprBinary::LessThan(e)
+            | ExprBinary::LessEqual(e) => (&e.lhs, &e.rhs),
+            _ => return,
+        };
+
+        self.scan_expr(lhs);
+        self.scan_expr(rhs);
+    }
+}
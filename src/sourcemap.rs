@@ -0,0 +1,117 @@
+//! A minimal Source Map v3 encoder. We only ever have one source file and
+//! map at statement granularity, so this doesn't need to be a general
+//! purpose sourcemap library -- just VLQ encoding plus the handful of JSON
+//! fields `dnscontrol`'s JS runtime (and anything reading its stack traces)
+//! expects.
+
+const BASE64_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// One `generated position -> source position` mapping. Lines/columns are
+/// 0-based, matching the Source Map v3 spec (the Lua parser's spans are
+/// 1-based; callers convert before pushing a mapping).
+#[derive(Debug, Clone, Copy)]
+pub struct Mapping {
+    pub generated_line: u32,
+    pub generated_column: u32,
+    pub source_line: u32,
+    pub source_column: u32,
+}
+
+pub struct SourceMap {
+    pub file: String,
+    pub source: String,
+    pub source_content: String,
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Renders the Source Map v3 JSON payload.
+    pub fn to_json(&self) -> String {
+        let mut sorted = self.mappings.clone();
+        sorted.sort_by_key(|m| (m.generated_line, m.generated_column));
+
+        let line_count = sorted.last().map(|m| m.generated_line + 1).unwrap_or(0);
+        let mut lines = vec![Vec::new(); line_count as usize];
+
+        let mut prev_generated_column = 0i64;
+        let mut prev_source_line = 0i64;
+        let mut prev_source_column = 0i64;
+        let mut prev_line = 0;
+
+        for mapping in &sorted {
+            if mapping.generated_line != prev_line {
+                prev_generated_column = 0;
+                prev_line = mapping.generated_line;
+            }
+
+            let generated_column = mapping.generated_column as i64;
+            let source_line = mapping.source_line as i64;
+            let source_column = mapping.source_column as i64;
+
+            let mut segment = String::new();
+            segment += &vlq_encode(generated_column - prev_generated_column);
+            segment += &vlq_encode(0); // source index: always the single source file
+            segment += &vlq_encode(source_line - prev_source_line);
+            segment += &vlq_encode(source_column - prev_source_column);
+            lines[mapping.generated_line as usize].push(segment);
+
+            prev_generated_column = generated_column;
+            prev_source_line = source_line;
+            prev_source_column = source_column;
+        }
+
+        let mappings = lines
+            .iter()
+            .map(|segments| segments.join(","))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        format!(
+            "{{\"version\":3,\"file\":{},\"sources\":[{}],\"sourcesContent\":[{}],\"names\":[],\"mappings\":{}}}",
+            json_string(&self.file),
+            json_string(&self.source),
+            json_string(&self.source_content),
+            json_string(&mappings),
+        )
+    }
+}
+
+fn vlq_encode(value: i64) -> String {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    let mut out = String::new();
+
+    loop {
+        let mut digit = vlq & 0x1f;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0x20;
+        }
+        out.push(BASE64_ALPHABET[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+
+    out
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}